@@ -1,19 +1,49 @@
 extern crate failure;
 extern crate handlebars;
+extern crate pretty;
+extern crate pulldown_cmark;
+extern crate rayon;
+extern crate serde_json;
 extern crate walkdir;
 
+use std::collections::HashMap;
 use std::fs::{create_dir_all, File};
 use std::io::{self, Read};
 use std::path::Path;
 
 use self::failure::ResultExt;
+use self::pretty::{Arena, DocAllocator};
+use self::pulldown_cmark::Parser;
+use self::rayon::prelude::*;
 
+use base::ast::{Expr, Pattern, SpannedExpr};
 use base::filename_to_module;
-use base::types::ArcType;
+use base::pos::BytePos;
+use base::symbol::Symbol;
+use base::types::{ArcType, ArgType, Type};
 use base::metadata::Metadata;
 use check::metadata::metadata;
 use {Compiler, Thread};
 
+// Column width that the rendered types try to wrap to.
+const TYPE_WIDTH: usize = 80;
+
+/// Selects the output produced by `generate`/`generate_for_path`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    Html,
+    Json,
+}
+
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Html => "html",
+            Format::Json => "json",
+        }
+    }
+}
+
 pub type Error = failure::Error;
 pub type Result<T> = ::std::result::Result<T, Error>;
 
@@ -21,6 +51,24 @@ pub type Result<T> = ::std::result::Result<T, Error>;
 pub struct Module<'a> {
     pub name: &'a str,
     pub record: Record<'a>,
+    pub sub_modules: Vec<String>,
+    pub github_source: Option<String>,
+    pub source_path: String,
+}
+
+#[derive(Serialize, PartialEq, Debug)]
+pub struct ModuleTree {
+    pub name: String,
+    pub full_name: String,
+    // `false` for a path segment that only exists because it's an ancestor of some other
+    // module (e.g. `std` when only `std.list`/`std.map` exist) — it has no page of its own.
+    pub has_module: bool,
+    pub children: Vec<ModuleTree>,
+}
+
+#[derive(Serialize)]
+struct Index<'a> {
+    modules: &'a [ModuleTree],
 }
 
 #[derive(Serialize, PartialEq, Debug)]
@@ -34,98 +82,719 @@ pub struct Field<'a> {
     pub name: &'a str,
     #[serde(rename = "type")]
     pub typ: String,
+    pub args: Vec<Argument>,
     pub comment: &'a str,
+    pub definition_line: Option<usize>,
+}
+
+#[derive(Serialize, PartialEq, Debug)]
+pub struct Argument {
+    pub implicit: bool,
+    pub name: String,
+}
+
+// Peels off `Forall` and alias wrappers so callers can match directly on `Type::Function`.
+// Almost every stdlib function is `forall a .. -> ..`, so skipping this step means the
+// `Function` arm below never fires for generic functions.
+fn skip_forall(typ: &ArcType) -> &ArcType {
+    let mut typ = typ.unresolved_type();
+    while let Type::Forall(_, ref inner) = **typ {
+        typ = inner.unresolved_type();
+    }
+    typ
+}
+
+// Walks the chain of `Type::Function`s wrapping `typ`, turning each argument into an
+// `Argument` so the template can render `?` markers for implicit arguments. `names` supplies
+// the real parameter names in declaration order (from the binding's own parameter list, when
+// one is available); any argument past the end of `names` falls back to a placeholder `argN`.
+fn collect_args(typ: &ArcType, names: &[String]) -> Vec<Argument> {
+    let mut args = Vec::new();
+    let mut current = skip_forall(typ);
+    let mut names = names.iter();
+    while let Type::Function(arg_type, _, ref ret) = **current {
+        let name = names
+            .next()
+            .cloned()
+            .unwrap_or_else(|| format!("arg{}", args.len() + 1));
+        args.push(Argument {
+            implicit: arg_type == ArgType::Implicit,
+            name,
+        });
+        current = skip_forall(ret);
+    }
+    args
+}
+
+fn doc_for_type<'a>(
+    arena: &'a Arena<'a>,
+    typ: &'a ArcType,
+    width: usize,
+) -> pretty::DocBuilder<'a, Arena<'a>> {
+    match **typ {
+        Type::Function(arg_type, ref from, ref to) => {
+            let marker = if arg_type == ArgType::Implicit { "?" } else { "" };
+            arena
+                .text(marker)
+                .append(doc_for_type(arena, from, width))
+                .append(arena.line())
+                .append("-> ")
+                .append(doc_for_type(arena, to, width))
+                .group()
+        }
+        Type::Forall(ref params, ref typ) => arena
+            .text("forall ")
+            .append(arena.concat(
+                params
+                    .iter()
+                    .map(|param| arena.text(param.id.as_ref().to_string()).append(" ")),
+            ))
+            .append(".")
+            .append(arena.line())
+            .append(doc_for_type(arena, typ, width))
+            .group(),
+        // Fields are separated by soft line breaks: the record stays on one line when it fits,
+        // otherwise each field goes on its own line. Each field's type is rendered (and, if
+        // needed, wrapped) independently, rather than reflowing the whole row as prose.
+        Type::Record(_) => {
+            let fields = typ.row_iter().map(|field| {
+                arena
+                    .text(field.name.as_ref().to_string())
+                    .append(" : ")
+                    .append(arena.text(render_type(&field.typ, width)))
+            });
+            arena
+                .text("{")
+                .append(
+                    arena
+                        .line()
+                        .append(arena.intersperse(fields, arena.text(",").append(arena.line())))
+                        .nest(4),
+                )
+                .append(arena.line())
+                .append("}")
+                .group()
+        }
+        _ => arena.reflow(&typ.to_string()),
+    }
+}
+
+// Renders `typ` with soft line breaks so long function signatures and record types wrap
+// instead of producing a single unreadable line.
+fn render_type(typ: &ArcType, width: usize) -> String {
+    let arena = Arena::new();
+    let doc = doc_for_type(&arena, typ, width);
+    let mut out = Vec::new();
+    match doc.1.render(width, &mut out) {
+        Ok(()) => String::from_utf8(out).unwrap_or_else(|_| typ.to_string()),
+        Err(_) => typ.to_string(),
+    }
+}
+
+// A compiled module's body is a chain of `let`/`type` bindings ending in the record that
+// becomes the module's value, e.g. `let map f xs = .. in type T = .. in { map, T }`. Walk
+// that chain and record the byte offset of each binding's own name token, so field lines
+// come from the binding the field actually refers to rather than wherever its name happens
+// to first appear in the file (an import, a doc comment, a call site, ...).
+fn collect_definition_positions(expr: &SpannedExpr<Symbol>) -> HashMap<String, BytePos> {
+    let mut positions = HashMap::new();
+    let mut expr = expr;
+    loop {
+        match expr.value {
+            Expr::LetBindings(ref bindings, ref body) => {
+                for binding in bindings {
+                    if let Pattern::Ident(ref id) = binding.name.value {
+                        positions.insert(
+                            id.name.as_ref().to_string(),
+                            binding.name.span.start,
+                        );
+                    }
+                }
+                expr = body;
+            }
+            Expr::TypeBindings(ref bindings, ref body) => {
+                for binding in bindings {
+                    positions.insert(
+                        binding.name.value.as_ref().to_string(),
+                        binding.name.span.start,
+                    );
+                }
+                expr = body;
+            }
+            _ => break,
+        }
+    }
+    positions
+}
+
+// Walks the same let-binding chain as `collect_definition_positions`, collecting each
+// value binding's real parameter names (implicit and explicit, in declaration order) so
+// `collect_args` can render e.g. `let f ?impl_arg arg` instead of placeholder `arg1`/`arg2`.
+fn collect_definition_args(expr: &SpannedExpr<Symbol>) -> HashMap<String, Vec<String>> {
+    let mut args = HashMap::new();
+    let mut expr = expr;
+    loop {
+        match expr.value {
+            Expr::LetBindings(ref bindings, ref body) => {
+                for binding in bindings {
+                    if let Pattern::Ident(ref id) = binding.name.value {
+                        let names = binding
+                            .args
+                            .iter()
+                            .map(|arg| arg.name.value.name.as_ref().to_string())
+                            .collect();
+                        args.insert(id.name.as_ref().to_string(), names);
+                    }
+                }
+                expr = body;
+            }
+            Expr::TypeBindings(_, ref body) => {
+                expr = body;
+            }
+            _ => break,
+        }
+    }
+    args
+}
+
+fn line_at_byte(source: &str, pos: BytePos) -> usize {
+    let offset = (pos.0 as usize).min(source.len());
+    source[..offset].matches('\n').count() + 1
+}
+
+fn definition_line(
+    source: Option<&str>,
+    positions: &HashMap<String, BytePos>,
+    name: &str,
+) -> Option<usize> {
+    let source = source?;
+    positions.get(name).map(|&pos| line_at_byte(source, pos))
 }
 
-pub fn record<'a>(typ: &'a ArcType, meta: &'a Metadata) -> Record<'a> {
+pub fn record<'a>(
+    typ: &'a ArcType,
+    meta: &'a Metadata,
+    expr: &SpannedExpr<Symbol>,
+    source: Option<&str>,
+) -> Record<'a> {
+    let positions = collect_definition_positions(expr);
+    let arg_names = collect_definition_args(expr);
+    let no_names = Vec::new();
+
     Record {
         types: typ.type_field_iter()
             .map(|field| Field {
                 name: field.name.as_ref(),
-                typ: field.typ.unresolved_type().to_string(),
+                typ: render_type(field.typ.unresolved_type(), TYPE_WIDTH),
+                args: collect_args(field.typ.unresolved_type(), &no_names),
                 comment: meta.module
                     .get(AsRef::<str>::as_ref(&field.name))
                     .and_then(|meta| meta.comment.as_ref().map(|s| &s[..]))
                     .unwrap_or(""),
+                definition_line: definition_line(source, &positions, field.name.as_ref()),
             })
             .collect(),
 
         values: typ.row_iter()
-            .map(|field| Field {
-                name: field.name.as_ref(),
-                typ: field.typ.to_string(),
+            .map(|field| {
+                let names = arg_names.get(field.name.as_ref()).unwrap_or(&no_names);
+                Field {
+                    name: field.name.as_ref(),
+                    typ: render_type(&field.typ, TYPE_WIDTH),
+                    args: collect_args(&field.typ, names),
 
-                comment: meta.module
-                    .get(AsRef::<str>::as_ref(&field.name))
-                    .and_then(|meta| meta.comment.as_ref().map(|s| &s[..]))
-                    .unwrap_or(""),
+                    comment: meta.module
+                        .get(AsRef::<str>::as_ref(&field.name))
+                        .and_then(|meta| meta.comment.as_ref().map(|s| &s[..]))
+                        .unwrap_or(""),
+                    definition_line: definition_line(source, &positions, field.name.as_ref()),
+                }
             })
             .collect(),
     }
 }
 
-pub fn generate<W>(out: &mut W, name: &str, typ: &ArcType, meta: &Metadata) -> Result<()>
+// Direct children of `name` in the dotted module namespace (`""` means the root). A child
+// is the next dotted segment past `name` for every module under it, whether or not that
+// segment is itself a real module (e.g. `std` is a child of `""` here even if only
+// `std.list`/`std.map` exist and there is no `std.glu`) — otherwise such modules would have
+// no ancestor in the tree and be unreachable from the generated index.
+fn direct_sub_modules(name: &str, names: &[String]) -> Vec<String> {
+    let prefix = if name.is_empty() {
+        String::new()
+    } else {
+        format!("{}.", name)
+    };
+
+    let mut children: Vec<String> = names
+        .iter()
+        .filter(|n| n.starts_with(&prefix) && n[prefix.len()..].len() > 0)
+        .map(|n| {
+            let rest = &n[prefix.len()..];
+            match rest.find('.') {
+                Some(dot) => format!("{}{}", prefix, &rest[..dot]),
+                None => n.clone(),
+            }
+        })
+        .collect();
+    children.sort();
+    children.dedup();
+    children
+}
+
+fn build_module_tree(name: &str, names: &[String]) -> ModuleTree {
+    let children = direct_sub_modules(name, names)
+        .into_iter()
+        .map(|child| build_module_tree(&child, names))
+        .collect();
+    ModuleTree {
+        name: name.rsplit('.').next().unwrap_or(name).to_string(),
+        full_name: name.to_string(),
+        has_module: names.iter().any(|n| n == name),
+        children,
+    }
+}
+
+struct MarkdownHelper;
+
+impl handlebars::HelperDef for MarkdownHelper {
+    fn call(
+        &self,
+        h: &handlebars::Helper,
+        _: &handlebars::Handlebars,
+        rc: &mut handlebars::RenderContext,
+    ) -> ::std::result::Result<(), handlebars::RenderError> {
+        let comment = h.param(0)
+            .and_then(|v| v.value().as_str())
+            .unwrap_or("");
+
+        if !comment.is_empty() {
+            let parser = Parser::new(comment);
+            let mut html = String::new();
+            pulldown_cmark::html::push_html(&mut html, parser);
+            rc.writer.write_all(html.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+// Renders `{{module_href current target}}` as a path from `current`'s output file to
+// `target`'s, relative to the output root. `target == ""` links to the root `index.html`.
+struct ModuleHrefHelper;
+
+impl handlebars::HelperDef for ModuleHrefHelper {
+    fn call(
+        &self,
+        h: &handlebars::Helper,
+        _: &handlebars::Handlebars,
+        rc: &mut handlebars::RenderContext,
+    ) -> ::std::result::Result<(), handlebars::RenderError> {
+        let current = h.param(0)
+            .and_then(|v| v.value().as_str())
+            .unwrap_or("");
+        let target = h.param(1)
+            .and_then(|v| v.value().as_str())
+            .unwrap_or("");
+
+        let href = if target.is_empty() {
+            let depth = current.matches('.').count();
+            format!("{}index.html", "../".repeat(depth))
+        } else {
+            let segments: Vec<&str> = current.split('.').filter(|s| !s.is_empty()).collect();
+            let current_dirs = segments.split_last().map_or(&[][..], |(_, dirs)| dirs);
+            let target_segments: Vec<&str> = target.split('.').collect();
+            let common = current_dirs
+                .iter()
+                .zip(&target_segments[..target_segments.len() - 1])
+                .take_while(|&(a, b)| a == b)
+                .count();
+            format!(
+                "{}{}.html",
+                "../".repeat(current_dirs.len() - common),
+                target_segments[common..].join("/")
+            )
+        };
+        rc.writer.write_all(href.as_bytes())?;
+        Ok(())
+    }
+}
+
+// Renders `{{src_link github_source source_path definition_line}}` as a `[src]` anchor
+// pointing at `{github_source}/{source_path}#L{definition_line}`. `source_path` is the
+// module's real relative file path (as opened on disk), not re-derived from the dotted
+// module name, since that mapping isn't guaranteed to round-trip losslessly. Renders
+// nothing if either `github_source` or `definition_line` is absent.
+struct SrcLinkHelper;
+
+impl handlebars::HelperDef for SrcLinkHelper {
+    fn call(
+        &self,
+        h: &handlebars::Helper,
+        _: &handlebars::Handlebars,
+        rc: &mut handlebars::RenderContext,
+    ) -> ::std::result::Result<(), handlebars::RenderError> {
+        let github_source = h.param(0).and_then(|v| v.value().as_str());
+        let source_path = h.param(1).and_then(|v| v.value().as_str()).unwrap_or("");
+        let line = h.param(2).and_then(|v| v.value().as_u64());
+
+        if let (Some(base), Some(line)) = (github_source, line) {
+            let html = format!(
+                r#" <a class="src-link" href="{}/{}#L{}">[src]</a>"#,
+                base, source_path, line
+            );
+            rc.writer.write_all(html.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+fn new_registry() -> handlebars::Handlebars {
+    let mut reg = handlebars::Handlebars::new();
+    reg.register_helper("markdown", Box::new(MarkdownHelper));
+    reg.register_helper("module_href", Box::new(ModuleHrefHelper));
+    reg.register_helper("src_link", Box::new(SrcLinkHelper));
+    reg
+}
+
+pub fn generate<W>(
+    out: &mut W,
+    name: &str,
+    typ: &ArcType,
+    meta: &Metadata,
+    expr: &SpannedExpr<Symbol>,
+    sub_modules: Vec<String>,
+    source: Option<&str>,
+    source_path: &str,
+    github_source: Option<&str>,
+    format: Format,
+) -> Result<()>
 where
     W: io::Write,
 {
     let r = Module {
         name,
-        record: record(typ, meta),
+        record: record(typ, meta, expr, source),
+        sub_modules,
+        github_source: github_source.map(str::to_string),
+        source_path: source_path.to_string(),
     };
 
     trace!("DOC: {:?}", r);
 
-    let reg = handlebars::Handlebars::new();
-    let module_template = include_str!("doc/module.html");
-    reg.render_template_to_write(module_template, &r, out)?;
+    match format {
+        Format::Html => {
+            let reg = new_registry();
+            let module_template = include_str!("doc/module.html");
+            reg.render_template_to_write(module_template, &r, out)?;
+        }
+        Format::Json => serde_json::to_writer_pretty(out, &r)?,
+    }
+    Ok(())
+}
+
+fn generate_index<W>(out: &mut W, modules: &[ModuleTree], format: Format) -> Result<()>
+where
+    W: io::Write,
+{
+    match format {
+        Format::Html => {
+            let mut reg = new_registry();
+            reg.register_partial("tree", include_str!("doc/tree.html"))?;
+            let index_template = include_str!("doc/index.html");
+            reg.render_template_to_write(index_template, &Index { modules }, out)?;
+        }
+        Format::Json => serde_json::to_writer_pretty(out, modules)?,
+    }
     Ok(())
 }
 
-pub fn generate_for_path<P, Q>(thread: &Thread, path: &P, out_path: &Q) -> Result<()>
+// Typechecks and renders a single `.glu` file. Runs on a rayon worker thread (of the pool
+// `generate_for_path` builds when `jobs` is given, otherwise of rayon's global pool), so it
+// creates its own `Compiler` rather than sharing one, but reads from the same `thread`.
+fn generate_one(
+    thread: &Thread,
+    entry: &walkdir::DirEntry,
+    name: &str,
+    module_names: &[String],
+    out_path: &Path,
+    github_source: Option<&str>,
+    format: Format,
+) -> Result<()> {
+    let mut input = File::open(&*entry.path()).with_context(|err| {
+        format!(
+            "Unable to open gluon file `{}`: {}",
+            entry.path().display(),
+            err
+        )
+    })?;
+    let mut content = String::new();
+    input.read_to_string(&mut content)?;
+
+    let (expr, typ) = Compiler::new().typecheck_str(thread, "basic", &content, None)?;
+    let (meta, _) = metadata(&*thread.get_env(), &expr);
+
+    create_dir_all(out_path.join(entry.path().parent().unwrap_or(Path::new(""))))?;
+
+    let module_out_path = out_path.join(entry.path().with_extension(format.extension()));
+    let mut doc_file = File::create(&*module_out_path).with_context(|err| {
+        format!(
+            "Unable to open output file `{}`: {}",
+            module_out_path.display(),
+            err
+        )
+    })?;
+
+    let source_path = entry
+        .path()
+        .to_str()
+        .ok_or_else(|| failure::err_msg("Non-UTF-8 filename"))?;
+
+    let sub_modules = direct_sub_modules(name, module_names);
+    generate(
+        &mut doc_file,
+        name,
+        &typ,
+        &meta,
+        &expr,
+        sub_modules,
+        Some(&content),
+        source_path,
+        github_source,
+        format,
+    )
+}
+
+pub fn generate_for_path<P, Q>(
+    thread: &Thread,
+    path: &P,
+    out_path: &Q,
+    github_source: Option<&str>,
+    jobs: Option<usize>,
+    format: Format,
+) -> Result<()>
 where
     P: ?Sized + AsRef<Path>,
     Q: ?Sized + AsRef<Path>,
 {
-    for entry in walkdir::WalkDir::new(path) {
-        let entry = entry?;
-        if !entry.file_type().is_file()
-            || entry.path().extension().and_then(|ext| ext.to_str()) != Some("glu")
-        {
-            continue;
-        }
-        let mut input = File::open(&*entry.path()).with_context(|err| {
-            format!(
-                "Unable to open gluon file `{}`: {}",
-                entry.path().display(),
-                err
-            )
-        })?;
-        let mut content = String::new();
-        input.read_to_string(&mut content)?;
+    let entries = walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| match entry {
+            Ok(entry) => {
+                if entry.file_type().is_file()
+                    && entry.path().extension().and_then(|ext| ext.to_str()) == Some("glu")
+                {
+                    Some(Ok(entry))
+                } else {
+                    None
+                }
+            }
+            Err(err) => Some(Err(err)),
+        })
+        .collect::<::std::result::Result<Vec<_>, _>>()?;
 
-        let (expr, typ) = Compiler::new().typecheck_str(thread, "basic", &content, None)?;
-        let (meta, _) = metadata(&*thread.get_env(), &expr);
+    let module_names = entries
+        .iter()
+        .map(|entry| -> Result<String> {
+            let path = entry
+                .path()
+                .to_str()
+                .ok_or_else(|| failure::err_msg("Non-UTF-8 filename"))?;
+            Ok(filename_to_module(path))
+        })
+        .collect::<Result<Vec<String>>>()?;
 
-        create_dir_all(
-            out_path
-                .as_ref()
-                .join(entry.path().parent().unwrap_or(Path::new(""))),
-        )?;
+    // `index.{ext}` is reserved for the generated module tree; a root-level `index.glu`
+    // would otherwise silently collide with it depending on write order.
+    if module_names.iter().any(|name| name == "index") {
+        return Err(failure::err_msg(
+            "a root-level module is named `index`, which collides with the generated index page",
+        ));
+    }
 
-        let out_path = out_path.as_ref().join(entry.path().with_extension("html"));
-        let mut doc_file = File::create(&*out_path).with_context(|err| {
-            format!(
-                "Unable to open output file `{}`: {}",
-                out_path.display(),
-                err
-            )
-        })?;
+    let out_path = out_path.as_ref();
+    let render_all = || -> Result<()> {
+        entries
+            .par_iter()
+            .zip(&module_names)
+            .map(|(entry, name)| {
+                generate_one(
+                    thread,
+                    entry,
+                    name,
+                    &module_names,
+                    out_path,
+                    github_source,
+                    format,
+                )
+            })
+            .collect::<Result<Vec<()>>>()?;
+        Ok(())
+    };
 
-        let name = filename_to_module(entry
-            .path()
-            .to_str()
-            .ok_or_else(|| failure::err_msg("Non-UTF-8 filename"))?);
-        generate(&mut doc_file, &name, &typ, &meta)?;
+    match jobs {
+        Some(jobs) => rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build()?
+            .install(render_all)?,
+        None => render_all()?,
     }
+
+    let tree = direct_sub_modules("", &module_names)
+        .into_iter()
+        .map(|name| build_module_tree(&name, &module_names))
+        .collect::<Vec<_>>();
+
+    let index_out_path = out_path.join(format!("index.{}", format.extension()));
+    let mut index_file = File::create(&*index_out_path).with_context(|err| {
+        format!(
+            "Unable to open output file `{}`: {}",
+            index_out_path.display(),
+            err
+        )
+    })?;
+    generate_index(&mut index_file, &tree, format)?;
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base::types::BuiltinType;
+
+    #[test]
+    fn collect_args_strips_forall_wrapper() {
+        let int_type: ArcType = Type::builtin(BuiltinType::Int);
+        let fn_type = Type::function(vec![int_type.clone()], int_type.clone());
+        let forall_type: ArcType = Type::Forall(Vec::new(), fn_type).into();
+
+        assert_eq!(collect_args(&forall_type, &[]).len(), 1);
+    }
+
+    #[test]
+    fn collect_args_without_forall_still_works() {
+        let int_type: ArcType = Type::builtin(BuiltinType::Int);
+        let fn_type = Type::function(vec![int_type.clone(), int_type.clone()], int_type.clone());
+
+        assert_eq!(collect_args(&fn_type, &[]).len(), 2);
+    }
+
+    #[test]
+    fn collect_args_uses_real_names_when_available() {
+        let int_type: ArcType = Type::builtin(BuiltinType::Int);
+        let fn_type = Type::function(vec![int_type.clone(), int_type.clone()], int_type.clone());
+
+        let names = vec!["x".to_string(), "y".to_string()];
+        let args = collect_args(&fn_type, &names);
+        assert_eq!(args[0].name, "x");
+        assert_eq!(args[1].name, "y");
+    }
+
+    #[test]
+    fn collect_args_falls_back_to_placeholder_names_past_the_end_of_names() {
+        let int_type: ArcType = Type::builtin(BuiltinType::Int);
+        let fn_type = Type::function(vec![int_type.clone(), int_type.clone()], int_type.clone());
+
+        let names = vec!["x".to_string()];
+        let args = collect_args(&fn_type, &names);
+        assert_eq!(args[0].name, "x");
+        assert_eq!(args[1].name, "arg2");
+    }
+
+    #[test]
+    fn direct_sub_modules_none_for_leaf() {
+        let names = vec!["std.list".to_string(), "std.map".to_string()];
+        assert!(direct_sub_modules("std.list", &names).is_empty());
+    }
+
+    #[test]
+    fn direct_sub_modules_multi_level() {
+        let names = vec![
+            "std.list".to_string(),
+            "std.map".to_string(),
+            "string".to_string(),
+        ];
+
+        let mut root = direct_sub_modules("", &names);
+        root.sort();
+        assert_eq!(root, vec!["std".to_string(), "string".to_string()]);
+
+        let mut std_children = direct_sub_modules("std", &names);
+        std_children.sort();
+        assert_eq!(
+            std_children,
+            vec!["std.list".to_string(), "std.map".to_string()]
+        );
+    }
+
+    #[test]
+    fn direct_sub_modules_synthesizes_orphan_ancestor() {
+        // No "std" module file exists, only its children, so "std" must still appear as an
+        // ancestor of "" or "std.list"/"std.map" would be unreachable from the tree.
+        let names = vec!["std.list".to_string(), "std.map".to_string()];
+        assert_eq!(direct_sub_modules("", &names), vec!["std".to_string()]);
+    }
+
+    #[test]
+    fn build_module_tree_marks_orphan_ancestor() {
+        let names = vec!["std.list".to_string(), "std.map".to_string()];
+        let tree = build_module_tree("std", &names);
+
+        assert_eq!(tree.full_name, "std");
+        assert!(!tree.has_module);
+        assert_eq!(tree.children.len(), 2);
+        assert!(tree.children.iter().all(|child| child.has_module));
+    }
+
+    #[test]
+    fn build_module_tree_leaf_has_module() {
+        let names = vec!["string".to_string()];
+        let tree = build_module_tree("string", &names);
+
+        assert!(tree.has_module);
+        assert!(tree.children.is_empty());
+    }
+
+    #[test]
+    fn module_href_links_to_root_index_relative_to_depth() {
+        let reg = new_registry();
+        let out = reg.render_template(r#"{{module_href "std.list" ""}}"#, &())
+            .unwrap();
+        assert_eq!(out, "../index.html");
+    }
+
+    #[test]
+    fn module_href_links_to_sibling_relative_path() {
+        let reg = new_registry();
+        let out = reg.render_template(r#"{{module_href "std.list" "std.map"}}"#, &())
+            .unwrap();
+        assert_eq!(out, "map.html");
+    }
+
+    #[test]
+    fn line_at_byte_counts_preceding_newlines() {
+        let source = "let x = 1\nlet y = 2\nlet z = 3\n";
+        assert_eq!(line_at_byte(source, BytePos(0)), 1);
+        assert_eq!(line_at_byte(source, BytePos(10)), 2);
+        assert_eq!(line_at_byte(source, BytePos(20)), 3);
+    }
+
+    #[test]
+    fn line_at_byte_clamps_to_source_len() {
+        let source = "let x = 1\n";
+        assert_eq!(line_at_byte(source, BytePos(1000)), 2);
+    }
+
+    #[test]
+    fn definition_line_looks_up_collected_positions() {
+        let source = "let x = 1\nlet y = 2\n";
+        let mut positions = HashMap::new();
+        positions.insert("x".to_string(), BytePos(0));
+        positions.insert("y".to_string(), BytePos(10));
+
+        assert_eq!(definition_line(Some(source), &positions, "x"), Some(1));
+        assert_eq!(definition_line(Some(source), &positions, "y"), Some(2));
+        assert_eq!(definition_line(Some(source), &positions, "missing"), None);
+        assert_eq!(definition_line(None, &positions, "x"), None);
+    }
+}